@@ -1,6 +1,32 @@
+use std::fmt;
 use std::fs;
 use std::process;
 
+#[derive(Debug)]
+struct ParseError {
+	index: usize,
+	token: String
+}
+
+impl fmt::Display for ParseError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "bad token {}: '{}'", self.index, self.token)
+	}
+}
+
+fn parse_input(contents: &str) -> Result<Vec<i32>, ParseError> {
+	contents
+		.trim()
+		.split('\n')
+		.enumerate()
+		.map(|(index, token)| {
+			token
+				.parse::<i32>()
+				.map_err(|_| ParseError { index, token: token.to_string() })
+		})
+		.collect()
+}
+
 fn get_cost(n: &i32) -> i32 {
 	return n/3 - 2
 }
@@ -28,11 +54,13 @@ fn part2(input: &Vec<i32>) -> i32 {
 fn main() {
 	let raw_input = fs::read_to_string("../input.txt");
 	let input = match &raw_input {
-		Ok(contents) => contents
-            .trim()
-			.split('\n')
-			.map(|x| { x.parse::<i32>().unwrap() })
-			.collect::<Vec<_>>(),
+		Ok(contents) => match parse_input(contents) {
+			Ok(input) => input,
+			Err(error) => {
+				eprintln!("Could not parse input: {}", error);
+				process::exit(1)
+			}
+		},
 		Err(error) => {
 			eprintln!("Could not read input: {}", error);
 			process::exit(1)
@@ -1,82 +1,288 @@
+use std::collections::HashSet;
+use std::fmt;
 use std::fs;
 use std::process;
+use std::str::FromStr;
 
-const DESIRED_RESULT: i32 = 19690720;
+type Word = i64;
 
-enum Opcode {
-	Add = 1,
-	Mult  = 2,
-	Terminate = 99
+const DESIRED_RESULT: Word = 19690720;
+
+#[derive(Debug, Clone, Copy)]
+enum Instruction {
+	Add { a: usize, b: usize, out: usize },
+	Mult { a: usize, b: usize, out: usize },
+	Terminate
+}
+
+impl Instruction {
+	fn decode(memory: &[Word], ip: usize) -> Result<Self, ExecutionError> {
+		let opcode = *memory
+			.get(ip)
+			.ok_or(ExecutionError::MissingOperands { position: ip })?;
+
+		match opcode {
+			1 | 2 => {
+				let operands = memory
+					.get(ip + 1..=ip + 3)
+					.ok_or(ExecutionError::MissingOperands { position: ip })?;
+				let (a, b, out) = (operands[0] as usize, operands[1] as usize, operands[2] as usize);
+
+				for address in [a, b, out] {
+					if address >= memory.len() {
+						return Err(ExecutionError::InvalidAddress { position: ip, address, accumulator: 0 });
+					}
+				}
+
+				Ok(if opcode == 1 {
+					Instruction::Add { a, b, out }
+				} else {
+					Instruction::Mult { a, b, out }
+				})
+			},
+			99 => Ok(Instruction::Terminate),
+			opcode => Err(ExecutionError::BadOpcode(opcode))
+		}
+	}
+
+	fn width(&self) -> usize {
+		match self {
+			Instruction::Terminate => 1,
+			_ => 4
+		}
+	}
+}
+
+enum State {
+	Running,
+	Halted(Word)
+}
+
+#[derive(Debug)]
+enum ExecutionError {
+	BadOpcode(Word),
+	Overflow { position: usize },
+	MissingOperands { position: usize },
+	InvalidAddress { position: usize, address: usize, accumulator: Word },
+	LoopDetected { position: usize, accumulator: Word },
+	NoSolutionFound
+}
+
+impl fmt::Display for ExecutionError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			ExecutionError::BadOpcode(opcode) => write!(f, "Bad Opcode: {}", opcode),
+			ExecutionError::Overflow { position } => {
+				write!(f, "Arithmetic overflow executing instruction at position {}", position)
+			},
+			ExecutionError::MissingOperands { position } => {
+				write!(f, "Instruction at position {} is missing operands", position)
+			},
+			ExecutionError::InvalidAddress { position, address, accumulator } => write!(
+				f,
+				"Instruction at position {} referenced out-of-range address {} (accumulator was {})",
+				position, address, accumulator
+			),
+			ExecutionError::LoopDetected { position, accumulator } => write!(
+				f,
+				"Loop detected at position {} (accumulator was {})",
+				position, accumulator
+			),
+			ExecutionError::NoSolutionFound => write!(f, "Could not find result")
+		}
+	}
+}
+
+struct Interpreter {
+	ip: usize,
+	accumulator: Word,
+	memory: Vec<Word>,
+	seen_positions: HashSet<usize>
+}
+
+impl Interpreter {
+	fn new(memory: Vec<Word>) -> Self {
+		Interpreter {
+			ip: 0,
+			accumulator: 0,
+			memory,
+			seen_positions: HashSet::new()
+		}
+	}
+
+	fn reset(&mut self, memory: &[Word]) {
+		self.ip = 0;
+		self.accumulator = 0;
+		self.memory.clear();
+		self.memory.extend_from_slice(memory);
+		self.seen_positions.clear();
+	}
+
+	fn step(&mut self) -> Result<State, ExecutionError> {
+		if !self.seen_positions.insert(self.ip) {
+			let (position, accumulator) = self.regdump();
+			return Err(ExecutionError::LoopDetected { position, accumulator });
+		}
+
+		let instruction = Instruction::decode(&self.memory, self.ip).map_err(|error| match error {
+			ExecutionError::InvalidAddress { position, address, .. } => {
+				ExecutionError::InvalidAddress { position, address, accumulator: self.accumulator }
+			},
+			other => other
+		})?;
+		match instruction {
+			Instruction::Add { a, b, out } => {
+				self.memory[out] = self.memory[a]
+					.checked_add(self.memory[b])
+					.ok_or(ExecutionError::Overflow { position: self.ip })?;
+				self.ip += instruction.width();
+				self.accumulator = self.memory[0];
+				Ok(State::Running)
+			},
+			Instruction::Mult { a, b, out } => {
+				self.memory[out] = self.memory[a]
+					.checked_mul(self.memory[b])
+					.ok_or(ExecutionError::Overflow { position: self.ip })?;
+				self.ip += instruction.width();
+				self.accumulator = self.memory[0];
+				Ok(State::Running)
+			},
+			Instruction::Terminate => {
+				self.accumulator = self.memory[0];
+				Ok(State::Halted(self.accumulator))
+			}
+		}
+	}
+
+	fn run(&mut self) -> Result<Word, ExecutionError> {
+		loop {
+			if let State::Halted(result) = self.step()? {
+				return Ok(result)
+			}
+		}
+	}
+
+	fn regdump(&self) -> (usize, Word) {
+		(self.ip, self.accumulator)
+	}
+}
+
+#[derive(Debug)]
+struct ProgramParseError {
+	index: usize,
+	token: String
+}
+
+impl fmt::Display for ProgramParseError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "bad token {}: '{}'", self.index, self.token)
+	}
 }
 
 #[derive(Clone)]
 struct Program {
-	memory: Vec<i32>
+	memory: Vec<Word>
+}
+
+impl FromStr for Program {
+	type Err = ProgramParseError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let memory = s
+			.trim()
+			.split(',')
+			.enumerate()
+			.map(|(index, token)| {
+				token
+					.parse::<Word>()
+					.map_err(|_| ProgramParseError { index, token: token.to_string() })
+			})
+			.collect::<Result<Vec<Word>, _>>()?;
+
+		Ok(Program { memory })
+	}
 }
 
 impl Program {
-	fn execute(&self, param1: i32, param2: i32) -> Result<i32, String> {
-		let mut local_memory = self.memory.clone();
-		local_memory[1] = param1;
-		local_memory[2] = param2;
-		for i in (0..local_memory.len()).step_by(4) {
-			let item = local_memory[i];
-			let operation = match item {
-				item if item == Opcode::Add as i32 => {
-					std::ops::Add::add
-				},
-				item if item == Opcode::Mult as i32 => {
-					std::ops::Mul::mul
-				},
-				item if item == Opcode::Terminate as i32 => {
-					 break;
-				},
-				_ => {
-					return Err("Bad Opcode".to_string());
+	fn execute(&self, param1: Word, param2: Word) -> Result<Word, ExecutionError> {
+		let mut interpreter = Interpreter::new(Vec::new());
+		self.execute_with(&mut interpreter, param1, param2)
+	}
+
+	fn execute_with(
+		&self,
+		interpreter: &mut Interpreter,
+		param1: Word,
+		param2: Word
+	) -> Result<Word, ExecutionError> {
+		interpreter.reset(&self.memory);
+		interpreter.memory[1] = param1;
+		interpreter.memory[2] = param2;
+		interpreter.run()
+	}
+
+	fn repair(&self, target: Word) -> Result<(Program, usize), ExecutionError> {
+		let mut ip = 0;
+		while ip < self.memory.len() {
+			let instruction = Instruction::decode(&self.memory, ip)?;
+
+			if matches!(instruction, Instruction::Add { .. } | Instruction::Mult { .. }) {
+				let flipped_opcode = if self.memory[ip] == 1 { 2 } else { 1 };
+				let mut candidate_memory = self.memory.clone();
+				candidate_memory[ip] = flipped_opcode;
+				let candidate = Program { memory: candidate_memory };
+
+				if let Ok(result) = Interpreter::new(candidate.memory.clone()).run() {
+					if result == target {
+						return Ok((candidate, ip));
+					}
 				}
-			};
+			}
 
-			let param_a_index = local_memory[i + 1] as usize;
-			let param_b_index = local_memory[i + 2] as usize;
-			let out_index = local_memory[i + 3] as usize;
-			local_memory[out_index] = operation(local_memory[param_a_index], local_memory[param_b_index])
+			if let Instruction::Terminate = instruction {
+				break;
+			}
+
+			ip += instruction.width();
 		}
 
-		return Ok(local_memory[0])
+		Err(ExecutionError::NoSolutionFound)
 	}
 }
 
-fn part1(program: &Program) -> Result<i32, String> {
+fn part1(program: &Program) -> Result<Word, ExecutionError> {
 	program.execute(12, 2)
 }
 
-fn part2(program: &Program) -> Result<(i32, i32), String> {
+fn part2(program: &Program) -> Result<(Word, Word), ExecutionError> {
+	let mut interpreter = Interpreter::new(Vec::new());
 	for i in 0..100 {
 		for j in 0..100 {
-			let result = program.execute(i, j)?;
+			let result = program.execute_with(&mut interpreter, i, j)?;
 			if result == DESIRED_RESULT {
 				return Ok((i, j))
 			}
 		}
 	}
 
-	Err("Could not find result".to_string())
+	Err(ExecutionError::NoSolutionFound)
 }
 
 fn main() {
 	let raw_input = fs::read_to_string("../input.txt");
-	let input = match &raw_input {
-		Ok(contents) => contents
-			.trim()
-			.split(',')
-			.map(|x| { x.parse::<i32>().unwrap() })
-			.collect::<Vec<_>>(),
+	let program = match &raw_input {
+		Ok(contents) => match contents.parse::<Program>() {
+			Ok(program) => program,
+			Err(error) => {
+				eprintln!("Could not parse input: {}", error);
+				process::exit(1)
+			}
+		},
 		Err(error) => {
 			eprintln!("Could not read input: {}", error);
 			process::exit(1)
 		}
 	};
-	let program = Program{memory: input};
 
 	match part1(&program) {
 		Ok(result) => println!("{}", result),
@@ -85,6 +291,13 @@ fn main() {
 
 	match part2(&program) {
 		Ok(result) => println!("{:?}", result),
-		Err(error) => println!("{}", error)
+		Err(error) => {
+			println!("{}", error);
+			println!("Falling back to opcode repair search...");
+			match program.repair(DESIRED_RESULT) {
+				Ok((_, index)) => println!("Repaired by flipping the opcode at index {}", index),
+				Err(repair_error) => println!("{}", repair_error)
+			}
+		}
 	}
 }